@@ -0,0 +1,302 @@
+use crate::prelude::*;
+
+// A parsed PEP 508 environment marker expression, e.g.
+// `python_version < "3.8" and extra == "security"`.
+//
+// Markers are small boolean expressions over a fixed set of environment
+// variables (`python_version`, `sys_platform`, etc.) and string/extra
+// literals, combined with `and`/`or`/parens. We parse them into a tree once
+// so that resolution can evaluate them directly against a target
+// environment, instead of re-parsing the same string over and over.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Marker {
+    And(Box<Marker>, Box<Marker>),
+    Or(Box<Marker>, Box<Marker>),
+    Comparison {
+        lhs: MarkerValue,
+        op: MarkerOp,
+        rhs: MarkerValue,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MarkerValue {
+    // A marker environment variable, e.g. `python_version` or `extra`.
+    Variable(String),
+    // A quoted string literal, e.g. `"3.8"`.
+    Literal(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkerOp {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    TildeEq,
+    ArbitraryEq,
+    In,
+    NotIn,
+}
+
+static MARKER_VARIABLES: &[&str] = &[
+    "python_version",
+    "python_full_version",
+    "os_name",
+    "sys_platform",
+    "platform_release",
+    "platform_system",
+    "platform_version",
+    "platform_machine",
+    "platform_python_implementation",
+    "implementation_name",
+    "implementation_version",
+    "extra",
+];
+
+impl Marker {
+    pub fn parse(input: &str) -> Result<Marker> {
+        let mut tokens = tokenize(input)?;
+        let marker = parse_or(&mut tokens)?;
+        if let Some(tok) = tokens.first() {
+            bail!("unexpected trailing token in marker: {:?}", tok);
+        }
+        Ok(marker)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Literal(String),
+    Op(String),
+    LParen,
+    RParen,
+    And,
+    Or,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = input.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            i += 1;
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            i += 1;
+        } else if c == '\'' || c == '"' {
+            let quote = c;
+            let start = i + 1;
+            let mut j = start;
+            while j < chars.len() && chars[j] != quote {
+                j += 1;
+            }
+            if j >= chars.len() {
+                bail!("unterminated string literal in marker: {}", input);
+            }
+            tokens.push(Token::Literal(chars[start..j].iter().collect()));
+            i = j + 1;
+        } else if "<>=!~".contains(c) {
+            let start = i;
+            let mut j = i + 1;
+            while j < chars.len() && "<>=!~".contains(chars[j]) {
+                j += 1;
+            }
+            tokens.push(Token::Op(chars[start..j].iter().collect()));
+            i = j;
+        } else if c.is_alphanumeric() || c == '_' || c == '.' {
+            let start = i;
+            let mut j = i + 1;
+            while j < chars.len()
+                && (chars[j].is_alphanumeric() || chars[j] == '_' || chars[j] == '.')
+            {
+                j += 1;
+            }
+            let word: String = chars[start..j].iter().collect();
+            match word.as_str() {
+                "and" => tokens.push(Token::And),
+                "or" => tokens.push(Token::Or),
+                "in" => tokens.push(Token::Op("in".to_string())),
+                "not" => {
+                    // "not in" is the only two-word operator. Check for a
+                    // word boundary after "in" too, so e.g. "not info" or
+                    // "not inside" aren't mis-tokenized as `Op("not in")`
+                    // followed by a bogus leftover identifier.
+                    let mut k = j;
+                    while k < chars.len() && chars[k].is_whitespace() {
+                        k += 1;
+                    }
+                    let is_identifier_char = |c: char| c.is_alphanumeric() || c == '_' || c == '.';
+                    let starts_with_in = chars[k..].iter().collect::<String>().starts_with("in")
+                        && !chars.get(k + 2).copied().is_some_and(is_identifier_char);
+                    if starts_with_in {
+                        tokens.push(Token::Op("not in".to_string()));
+                        j = k + 2;
+                    } else {
+                        bail!("unexpected 'not' without 'in' in marker: {}", input);
+                    }
+                }
+                _ => tokens.push(Token::Ident(word)),
+            }
+            i = j;
+        } else {
+            bail!("unexpected character {:?} in marker: {}", c, input);
+        }
+    }
+    Ok(tokens)
+}
+
+fn parse_or(tokens: &mut Vec<Token>) -> Result<Marker> {
+    let mut lhs = parse_and(tokens)?;
+    while matches!(tokens.first(), Some(Token::Or)) {
+        tokens.remove(0);
+        let rhs = parse_and(tokens)?;
+        lhs = Marker::Or(Box::new(lhs), Box::new(rhs));
+    }
+    Ok(lhs)
+}
+
+fn parse_and(tokens: &mut Vec<Token>) -> Result<Marker> {
+    let mut lhs = parse_atom(tokens)?;
+    while matches!(tokens.first(), Some(Token::And)) {
+        tokens.remove(0);
+        let rhs = parse_atom(tokens)?;
+        lhs = Marker::And(Box::new(lhs), Box::new(rhs));
+    }
+    Ok(lhs)
+}
+
+fn parse_atom(tokens: &mut Vec<Token>) -> Result<Marker> {
+    if matches!(tokens.first(), Some(Token::LParen)) {
+        tokens.remove(0);
+        let inner = parse_or(tokens)?;
+        match tokens.first() {
+            Some(Token::RParen) => {
+                tokens.remove(0);
+            }
+            _ => bail!("expected closing paren in marker"),
+        }
+        return Ok(inner);
+    }
+
+    let lhs = parse_value(tokens)?;
+    let op = match tokens.first() {
+        Some(Token::Op(op_str)) => {
+            let op = parse_op(op_str)?;
+            tokens.remove(0);
+            op
+        }
+        other => bail!("expected comparison operator in marker, got {:?}", other),
+    };
+    let rhs = parse_value(tokens)?;
+    Ok(Marker::Comparison { lhs, op, rhs })
+}
+
+fn parse_value(tokens: &mut Vec<Token>) -> Result<MarkerValue> {
+    match tokens.first().cloned() {
+        Some(Token::Ident(name)) => {
+            tokens.remove(0);
+            if !MARKER_VARIABLES.contains(&name.as_str()) {
+                bail!("unknown marker variable: {}", name);
+            }
+            Ok(MarkerValue::Variable(name))
+        }
+        Some(Token::Literal(s)) => {
+            tokens.remove(0);
+            Ok(MarkerValue::Literal(s))
+        }
+        other => bail!(
+            "expected marker variable or string literal, got {:?}",
+            other
+        ),
+    }
+}
+
+fn parse_op(op_str: &str) -> Result<MarkerOp> {
+    Ok(match op_str {
+        "==" => MarkerOp::Eq,
+        "!=" => MarkerOp::NotEq,
+        "<" => MarkerOp::Lt,
+        "<=" => MarkerOp::LtEq,
+        ">" => MarkerOp::Gt,
+        ">=" => MarkerOp::GtEq,
+        "~=" => MarkerOp::TildeEq,
+        "===" => MarkerOp::ArbitraryEq,
+        "in" => MarkerOp::In,
+        "not in" => MarkerOp::NotIn,
+        _ => bail!("unrecognized marker operator: {}", op_str),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_comparison() {
+        let got = Marker::parse(r#"python_version < "3.8""#).unwrap();
+        assert_eq!(
+            got,
+            Marker::Comparison {
+                lhs: MarkerValue::Variable("python_version".to_string()),
+                op: MarkerOp::Lt,
+                rhs: MarkerValue::Literal("3.8".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_and_or() {
+        let got = Marker::parse(r#"python_version < "3.8" and extra == "security""#).unwrap();
+        assert!(matches!(got, Marker::And(_, _)));
+
+        let got = Marker::parse(r#"os_name == "posix" or os_name == "nt""#).unwrap();
+        assert!(matches!(got, Marker::Or(_, _)));
+    }
+
+    #[test]
+    fn test_parse_parens() {
+        let got =
+            Marker::parse(r#"(extra == "a" or extra == "b") and python_version < "3.8""#).unwrap();
+        assert!(matches!(got, Marker::And(_, _)));
+    }
+
+    #[test]
+    fn test_parse_not_in() {
+        let got = Marker::parse(r#"platform_machine not in "x86_64 aarch64""#).unwrap();
+        assert_eq!(
+            got,
+            Marker::Comparison {
+                lhs: MarkerValue::Variable("platform_machine".to_string()),
+                op: MarkerOp::NotIn,
+                rhs: MarkerValue::Literal("x86_64 aarch64".to_string()),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(Marker::parse("").is_err());
+        assert!(Marker::parse("unknown_variable == \"x\"").is_err());
+        assert!(Marker::parse("python_version < \"3.8\" extra").is_err());
+        assert!(Marker::parse("python_version not \"3.8\"").is_err());
+    }
+
+    #[test]
+    fn test_parse_not_requires_word_boundary_after_in() {
+        // "not info"/"not inside" must not be mis-tokenized as `not in`
+        // followed by a leftover "fo"/"side" identifier -- there's a word
+        // boundary check after "in" now, so these are just rejected as
+        // invalid markers, same as any other bare "not".
+        assert!(Marker::parse(r#"python_version not info"#).is_err());
+        assert!(Marker::parse(r#"python_version not inside"#).is_err());
+    }
+}
@@ -1,11 +1,18 @@
 use crate::prelude::*;
 
+use std::ffi::{CStr, CString};
 use std::fs::File;
-use std::io::Write;
+use std::io::{Read, Write};
+use std::os::raw::{c_char, c_void};
 use std::os::unix::{fs::PermissionsExt, io::AsRawFd};
 use std::path::PathBuf;
 use std::process::Command;
 
+#[link(name = "dl")]
+extern "C" {
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+}
+
 // Ordered from most-preferred to least-preferred (so e.g. 64-bit platforms should
 // usually go first)
 static GLIBC_DETECTORS: Lazy<Vec<(&str, &[u8])>> = Lazy::new(|| {
@@ -54,8 +61,122 @@ static GLIBC_DETECTORS: Lazy<Vec<(&str, &[u8])>> = Lazy::new(|| {
     glibc_detectors
 });
 
-static GLIBC_VERSION_RE: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"^([0-9]+)\.([0-9]+)").unwrap());
+static GLIBC_VERSION_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^([0-9]+)\.([0-9]+)").unwrap());
+
+// Looks up glibc's own gnu_get_libc_version() in whatever's already loaded into
+// this process, and calls it directly -- no subprocess, no /proc, no embedded
+// detector binaries required. Returns Ok(None) if the symbol isn't found
+// anywhere (e.g. we're running against musl, which doesn't have it), so the
+// caller can fall back to the detector-binary dance below.
+fn native_glibc_version() -> Result<Option<(u32, u32)>> {
+    let symbol = CString::new("gnu_get_libc_version").unwrap();
+    // A null handle tells dlsym to search the symbols of the calling process
+    // itself, which includes every shared library already loaded into it --
+    // equivalent to RTLD_DEFAULT.
+    let func_ptr = unsafe { dlsym(std::ptr::null_mut(), symbol.as_ptr()) };
+    if func_ptr.is_null() {
+        return Ok(None);
+    }
+    let gnu_get_libc_version: extern "C" fn() -> *const c_char =
+        unsafe { std::mem::transmute(func_ptr) };
+    let version_cstr = unsafe { CStr::from_ptr(gnu_get_libc_version()) };
+    let version_str = version_cstr.to_string_lossy();
+    match GLIBC_VERSION_RE.captures(&version_str) {
+        None => bail!("unexpected glibc version number: {:?}", version_str),
+        Some(captures) => {
+            let major: u32 = captures.get(1).unwrap().as_str().parse()?;
+            let minor: u32 = captures.get(2).unwrap().as_str().parse()?;
+            Ok(Some((major, minor)))
+        }
+    }
+}
+
+// ELF e_machine values we care about; see
+// https://refspecs.linuxfoundation.org/elf/gabi4+/ch4.eheader.html
+const EM_386: u16 = 3;
+const EM_ARM: u16 = 40;
+const EM_X86_64: u16 = 62;
+const EM_S390: u16 = 22;
+const EM_PPC64: u16 = 21;
+const EM_AARCH64: u16 = 183;
+
+const ELFCLASS32: u8 = 1;
+const ELFCLASS64: u8 = 2;
+
+// Figures out our own py_arch tag by reading the ELF header of the currently
+// running executable, instead of launching a subprocess to ask. Falls back to
+// the compile-time target arch if /proc isn't mounted.
+fn native_py_arch() -> Result<&'static str> {
+    match elf_header_arch() {
+        Ok(arch) => Ok(arch),
+        Err(e) => {
+            debug!("couldn't read ELF header of /proc/self/exe: {}", e);
+            cfg_target_arch()
+        }
+    }
+}
+
+fn elf_header_arch() -> Result<&'static str> {
+    let mut header = [0u8; 20];
+    File::open("/proc/self/exe")?.read_exact(&mut header)?;
+    if &header[0..4] != b"\x7fELF" {
+        bail!("not an ELF file");
+    }
+    let ei_class = header[4];
+    // e_machine lives at the same offset (16..18 is e_type, 18..20 is
+    // e_machine) in both the 32-bit and 64-bit header layouts.
+    let e_machine = u16::from_ne_bytes([header[18], header[19]]);
+    match (e_machine, ei_class) {
+        (EM_X86_64, ELFCLASS64) => Ok("x86_64"),
+        (EM_386, ELFCLASS32) => Ok("i686"),
+        (EM_AARCH64, ELFCLASS64) => Ok("aarch64"),
+        (EM_ARM, ELFCLASS32) => Ok("armv7l"),
+        (EM_PPC64, ELFCLASS64) => Ok("ppc64le"),
+        (EM_S390, ELFCLASS64) => Ok("s390x"),
+        (machine, class) => bail!("unrecognized ELF machine/class: {}/{}", machine, class),
+    }
+}
+
+// Other py_arch tags a multiarch host running `native_arch` can typically
+// also execute (e.g. 32-bit i686 binaries on an x86_64 host), grouped the
+// same way `GLIBC_DETECTORS` groups its precompiled binaries. Most-preferred
+// first.
+fn compatible_py_arches(native_arch: &str) -> &'static [&'static str] {
+    match native_arch {
+        "x86_64" => &["i686"],
+        "aarch64" => &["armv7l"],
+        _ => &[],
+    }
+}
+
+// The dynamic loader a secondary arch's binaries are linked against. Its
+// presence is what actually makes that arch executable on this host -- the
+// same signal the musllinux detection below uses for its loader.
+fn py_arch_loader(py_arch: &str) -> Option<&'static str> {
+    match py_arch {
+        "i686" => Some("/lib/ld-linux.so.2"),
+        "armv7l" => Some("/lib/ld-linux-armhf.so.3"),
+        _ => None,
+    }
+}
+
+fn cfg_target_arch() -> Result<&'static str> {
+    if cfg!(target_arch = "x86_64") {
+        Ok("x86_64")
+    } else if cfg!(target_arch = "x86") {
+        Ok("i686")
+    } else if cfg!(target_arch = "aarch64") {
+        Ok("aarch64")
+    } else if cfg!(target_arch = "arm") {
+        Ok("armv7l")
+    } else if cfg!(target_arch = "powerpc64") {
+        Ok("ppc64le")
+    } else if cfg!(target_arch = "s390x") {
+        Ok("s390x")
+    } else {
+        bail!("unsupported target_arch")
+    }
+}
 
 fn glibc_version(py_arch: &str, detector: &[u8]) -> Result<Option<(u32, u32)>> {
     // This is a stupid hack to run 'detector' as an executable, with the guarantees
@@ -74,8 +195,7 @@ fn glibc_version(py_arch: &str, detector: &[u8]) -> Result<Option<(u32, u32)>> {
     // Have to re-open because exec() requires that the file has no open writers
     let f_readonly = File::open(format!("/proc/self/fd/{}", f.as_raw_fd()))?;
     drop(f);
-    let output =
-        Command::new(format!("/proc/self/fd/{}", f_readonly.as_raw_fd())).output()?;
+    let output = Command::new(format!("/proc/self/fd/{}", f_readonly.as_raw_fd())).output()?;
     if !output.status.success() {
         debug!("non-zero return for {}: {}", py_arch, output.status);
         Ok(None)
@@ -127,17 +247,90 @@ fn musl_version(loader: &PathBuf) -> Result<(u32, u32)> {
     }
 }
 
+// Maps each legacy manylinux name to the glibc floor it requires and the
+// py_arch values it was ever published for, newest-floor-first so that the
+// aliases come out ordered most-preferred to least-preferred.
+static LEGACY_MANYLINUX_ALIASES: &[(&str, (u32, u32), &[&str])] = &[
+    (
+        "manylinux2014",
+        (2, 17),
+        &["x86_64", "i686", "aarch64", "armv7l", "ppc64le", "s390x"],
+    ),
+    ("manylinux2010", (2, 12), &["x86_64", "i686"]),
+    ("manylinux1", (2, 5), &["x86_64", "i686"]),
+];
+
+// The oldest glibc a manylinux tag was ever defined for, per PEP 600 --
+// below this there's no point walking the ladder any further since no wheel
+// could possibly have been built against it.
+fn glibc_floor(py_arch: &str) -> (u32, u32) {
+    match py_arch {
+        "x86_64" | "i686" => (2, 4),
+        _ => (2, 16),
+    }
+}
+
+// A wheel built for manylinux_M_N is compatible with any host whose glibc is
+// >= M.N, so a host running glibc 2.31 is just as able to run a
+// manylinux_2_20 wheel as a manylinux_2_31 one. Emit the full ladder of tags
+// from the detected version down to the arch's floor (not just the exact
+// version) so we don't wrongly reject wheels built for an older, perfectly
+// compatible floor. Legacy aliases are spliced in immediately after the
+// ladder reaches their corresponding floor, mirroring how `packaging`'s
+// `_manylinux.platform_tags()` walks this same ladder.
+fn push_manylinux_tags(all_tags: &mut Vec<String>, major: u32, minor: u32, py_arch: &str) {
+    let (floor_major, floor_minor) = glibc_floor(py_arch);
+    if major != floor_major || minor < floor_minor {
+        return;
+    }
+    for m in (floor_minor..=minor).rev() {
+        all_tags.push(format!("manylinux_{}_{}_{}", major, m, py_arch));
+        for (legacy_name, floor, arches) in LEGACY_MANYLINUX_ALIASES.iter() {
+            if *floor == (major, m) && arches.contains(&py_arch) {
+                all_tags.push(format!("{}_{}", legacy_name, py_arch));
+            }
+        }
+    }
+}
+
 pub fn core_platform_tags() -> Result<Vec<String>> {
     let mut all_tags: Vec<String> = Vec::new();
 
-    for (py_arch, detector) in GLIBC_DETECTORS.iter() {
-        match glibc_version(py_arch, detector) {
-            Err(e) => warn!("error checking glibc version on {}: {}", py_arch, e),
-            Ok(None) => {}
-            Ok(Some((major, minor))) => {
-                all_tags.push(format!("manylinux_{}_{}_{}", major, minor, py_arch))
+    match native_glibc_version() {
+        Ok(Some((major, minor))) => match native_py_arch() {
+            Ok(py_arch) => {
+                push_manylinux_tags(&mut all_tags, major, minor, py_arch);
+                // The running process only tells us its own arch, but a
+                // multiarch host can often also run other arches (e.g. i686
+                // on an x86_64 host) against the same system glibc -- check
+                // each one's loader rather than only ever reporting the one
+                // arch this process happens to be.
+                for secondary_arch in compatible_py_arches(py_arch) {
+                    let executable = py_arch_loader(secondary_arch)
+                        .map(|loader| std::path::Path::new(loader).exists())
+                        .unwrap_or(false);
+                    if executable {
+                        push_manylinux_tags(&mut all_tags, major, minor, secondary_arch);
+                    }
+                }
+            }
+            Err(e) => warn!("error determining native arch: {}", e),
+        },
+        Ok(None) => {
+            // gnu_get_libc_version isn't loaded anywhere in this process (e.g.
+            // we're running on musl), so fall back to the old trick of
+            // launching the precompiled per-arch detector binaries.
+            for (py_arch, detector) in GLIBC_DETECTORS.iter() {
+                match glibc_version(py_arch, detector) {
+                    Err(e) => warn!("error checking glibc version on {}: {}", py_arch, e),
+                    Ok(None) => {}
+                    Ok(Some((major, minor))) => {
+                        push_manylinux_tags(&mut all_tags, major, minor, py_arch)
+                    }
+                }
             }
         }
+        Err(e) => warn!("error checking native glibc version: {}", e),
     }
 
     // Put musllinux after manylinux, since at least for now, manylinux is a smoother
@@ -163,3 +356,97 @@ pub fn core_platform_tags() -> Result<Vec<String>> {
 
     Ok(all_tags)
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_push_manylinux_tags_glibc_2_31_x86_64() {
+        let mut all_tags: Vec<String> = Vec::new();
+        push_manylinux_tags(&mut all_tags, 2, 31, "x86_64");
+        assert_eq!(
+            all_tags,
+            vec![
+                "manylinux_2_31_x86_64",
+                "manylinux_2_30_x86_64",
+                "manylinux_2_29_x86_64",
+                "manylinux_2_28_x86_64",
+                "manylinux_2_27_x86_64",
+                "manylinux_2_26_x86_64",
+                "manylinux_2_25_x86_64",
+                "manylinux_2_24_x86_64",
+                "manylinux_2_23_x86_64",
+                "manylinux_2_22_x86_64",
+                "manylinux_2_21_x86_64",
+                "manylinux_2_20_x86_64",
+                "manylinux_2_19_x86_64",
+                "manylinux_2_18_x86_64",
+                "manylinux_2_17_x86_64",
+                "manylinux2014_x86_64",
+                "manylinux_2_16_x86_64",
+                "manylinux_2_15_x86_64",
+                "manylinux_2_14_x86_64",
+                "manylinux_2_13_x86_64",
+                "manylinux_2_12_x86_64",
+                "manylinux2010_x86_64",
+                "manylinux_2_11_x86_64",
+                "manylinux_2_10_x86_64",
+                "manylinux_2_9_x86_64",
+                "manylinux_2_8_x86_64",
+                "manylinux_2_7_x86_64",
+                "manylinux_2_6_x86_64",
+                "manylinux_2_5_x86_64",
+                "manylinux1_x86_64",
+                "manylinux_2_4_x86_64",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_push_manylinux_tags_below_every_legacy_floor() {
+        let mut all_tags: Vec<String> = Vec::new();
+        push_manylinux_tags(&mut all_tags, 2, 4, "x86_64");
+        assert_eq!(all_tags, vec!["manylinux_2_4_x86_64"]);
+    }
+
+    #[test]
+    fn test_push_manylinux_tags_below_arch_floor_emits_nothing() {
+        // 2.3 is below even the x86_64/i686 floor of 2.4, so there's no
+        // ladder to walk at all.
+        let mut all_tags: Vec<String> = Vec::new();
+        push_manylinux_tags(&mut all_tags, 2, 3, "x86_64");
+        assert_eq!(all_tags, Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_push_manylinux_tags_arch_not_eligible_for_legacy_alias() {
+        // manylinux1/manylinux2010 were never published for aarch64, so a
+        // glibc floor that would otherwise qualify still shouldn't produce
+        // those aliases for this arch. aarch64's floor is 2.16.
+        let mut all_tags: Vec<String> = Vec::new();
+        push_manylinux_tags(&mut all_tags, 2, 17, "aarch64");
+        assert_eq!(
+            all_tags,
+            vec![
+                "manylinux_2_17_aarch64",
+                "manylinux2014_aarch64",
+                "manylinux_2_16_aarch64",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compatible_py_arches() {
+        assert_eq!(compatible_py_arches("x86_64"), &["i686"]);
+        assert_eq!(compatible_py_arches("aarch64"), &["armv7l"]);
+        assert_eq!(compatible_py_arches("i686"), &[] as &[&str]);
+    }
+
+    #[test]
+    fn test_py_arch_loader() {
+        assert_eq!(py_arch_loader("i686"), Some("/lib/ld-linux.so.2"));
+        assert_eq!(py_arch_loader("armv7l"), Some("/lib/ld-linux-armhf.so.3"));
+        assert_eq!(py_arch_loader("x86_64"), None);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::prelude::*;
+
+use std::ffi::CString;
+use std::os::raw::{c_char, c_void};
+
+#[link(name = "c")]
+extern "C" {
+    fn sysctlbyname(
+        name: *const c_char,
+        oldp: *mut c_void,
+        oldlenp: *mut usize,
+        newp: *const c_void,
+        newlen: usize,
+    ) -> i32;
+}
+
+// Equivalent of `sysctl -n kern.osproductversion`, which is what CPython's
+// platform.mac_ver() uses under the hood. Returns e.g. "13.4" or "10.15.7".
+fn sysctl_string(name: &str) -> Result<String> {
+    let name = CString::new(name)?;
+    let mut len: usize = 0;
+    // First call with a null buffer just finds out how big the value is.
+    let rc = unsafe {
+        sysctlbyname(
+            name.as_ptr(),
+            std::ptr::null_mut(),
+            &mut len as *mut usize,
+            std::ptr::null(),
+            0,
+        )
+    };
+    if rc != 0 {
+        Err(std::io::Error::last_os_error())?
+    }
+    let mut buf: Vec<u8> = vec![0; len];
+    let rc = unsafe {
+        sysctlbyname(
+            name.as_ptr(),
+            buf.as_mut_ptr() as *mut c_void,
+            &mut len as *mut usize,
+            std::ptr::null(),
+            0,
+        )
+    };
+    if rc != 0 {
+        Err(std::io::Error::last_os_error())?
+    }
+    // len includes the trailing NUL
+    buf.truncate(len.saturating_sub(1));
+    Ok(String::from_utf8(buf)?)
+}
+
+static OS_VERSION_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^([0-9]+)\.([0-9]+)").unwrap());
+
+fn os_version() -> Result<(u32, u32)> {
+    let version_str = sysctl_string("kern.osproductversion")?;
+    match OS_VERSION_RE.captures(&version_str) {
+        None => bail!("unexpected macOS version number: {:?}", version_str),
+        Some(captures) => {
+            let major: u32 = captures.get(1).unwrap().as_str().parse()?;
+            let minor: u32 = captures.get(2).unwrap().as_str().parse()?;
+            Ok((major, minor))
+        }
+    }
+}
+
+// maps our arch to the arches it's also considered compatible with, most-specific
+// first (this is the "fat"/universal binary fallback chain)
+fn compatible_arches(arch: &str) -> &'static [&'static str] {
+    match arch {
+        "arm64" => &["arm64", "universal2"],
+        "x86_64" => &["x86_64", "universal2", "intel", "fat64", "fat32"],
+        _ => &[],
+    }
+}
+
+fn native_arch() -> &'static str {
+    if cfg!(target_arch = "aarch64") {
+        "arm64"
+    } else {
+        "x86_64"
+    }
+}
+
+// The pure version/arch-ladder math, split out from `os_version()`'s sysctl
+// call and `native_arch()`'s compile-time arch check so it can be tested
+// without touching real hardware.
+fn platform_tags_for_version(major: u32, minor: u32, arches: &[&str]) -> Vec<String> {
+    let mut all_tags: Vec<String> = Vec::new();
+
+    // Sort primarily by OS version (most recent first), with arch only a
+    // tie-break within a version -- otherwise an older narrow-arch tag would
+    // outrank a newer broader-arch one, which is backwards for wheel
+    // selection.
+    if major >= 11 {
+        // Current-style versioning: macosx_{major}_0_{arch} for every major
+        // version down to 11...
+        for m in (11..=major).rev() {
+            for arch in arches {
+                all_tags.push(format!("macosx_{}_0_{}", m, arch));
+            }
+        }
+        // ...plus the legacy 10.16-and-below aliases, since Big Sur reports
+        // itself as 10.16 to tooling that doesn't know better.
+        for m in (0..=16).rev() {
+            for arch in arches {
+                all_tags.push(format!("macosx_10_{}_{}", m, arch));
+            }
+        }
+    } else {
+        for m in (0..=minor).rev() {
+            for arch in arches {
+                all_tags.push(format!("macosx_10_{}_{}", m, arch));
+            }
+        }
+    }
+
+    all_tags
+}
+
+pub fn core_platform_tags() -> Result<Vec<String>> {
+    let (major, minor) = os_version()?;
+    let arches = compatible_arches(native_arch());
+    Ok(platform_tags_for_version(major, minor, arches))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_big_sur_and_later_ladder() {
+        let got = platform_tags_for_version(13, 4, &["arm64"]);
+        let expected: Vec<String> = vec![
+            "macosx_13_0_arm64",
+            "macosx_12_0_arm64",
+            "macosx_11_0_arm64",
+            "macosx_10_16_arm64",
+            "macosx_10_15_arm64",
+            "macosx_10_14_arm64",
+            "macosx_10_13_arm64",
+            "macosx_10_12_arm64",
+            "macosx_10_11_arm64",
+            "macosx_10_10_arm64",
+            "macosx_10_9_arm64",
+            "macosx_10_8_arm64",
+            "macosx_10_7_arm64",
+            "macosx_10_6_arm64",
+            "macosx_10_5_arm64",
+            "macosx_10_4_arm64",
+            "macosx_10_3_arm64",
+            "macosx_10_2_arm64",
+            "macosx_10_1_arm64",
+            "macosx_10_0_arm64",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn test_pre_big_sur_ladder() {
+        let got = platform_tags_for_version(10, 15, &["x86_64"]);
+        let expected: Vec<String> = vec![
+            "macosx_10_15_x86_64",
+            "macosx_10_14_x86_64",
+            "macosx_10_13_x86_64",
+            "macosx_10_12_x86_64",
+            "macosx_10_11_x86_64",
+            "macosx_10_10_x86_64",
+            "macosx_10_9_x86_64",
+            "macosx_10_8_x86_64",
+            "macosx_10_7_x86_64",
+            "macosx_10_6_x86_64",
+            "macosx_10_5_x86_64",
+            "macosx_10_4_x86_64",
+            "macosx_10_3_x86_64",
+            "macosx_10_2_x86_64",
+            "macosx_10_1_x86_64",
+            "macosx_10_0_x86_64",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+        assert_eq!(got, expected);
+    }
+
+    #[test]
+    fn test_arch_is_tie_break_within_version() {
+        let got = platform_tags_for_version(11, 0, &["arm64", "universal2"]);
+        // Both arch tags for macosx_11_0 come before either arch tag for
+        // macosx_10_16 -- version sorts before arch, not the other way
+        // around.
+        assert_eq!(
+            &got[..2],
+            &[
+                "macosx_11_0_arm64".to_string(),
+                "macosx_11_0_universal2".to_string()
+            ]
+        );
+        assert_eq!(got[2], "macosx_10_16_arm64");
+    }
+
+    #[test]
+    fn test_compatible_arches() {
+        assert_eq!(compatible_arches("arm64"), &["arm64", "universal2"]);
+        assert_eq!(
+            compatible_arches("x86_64"),
+            &["x86_64", "universal2", "intel", "fat64", "fat32"]
+        );
+        assert_eq!(compatible_arches("unknown"), &[] as &[&str]);
+    }
+}
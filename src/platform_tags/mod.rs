@@ -0,0 +1,23 @@
+use crate::prelude::*;
+
+#[cfg(target_os = "linux")]
+mod linux;
+#[cfg(target_os = "linux")]
+pub use linux::core_platform_tags;
+
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(target_os = "macos")]
+pub use macos::core_platform_tags;
+
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(target_os = "windows")]
+pub use windows::platform_tags;
+
+// A single cfg-independent entry point, so callers don't have to know that
+// Windows names its function differently from everyone else.
+#[cfg(not(target_os = "windows"))]
+pub fn platform_tags() -> Result<Vec<String>> {
+    core_platform_tags()
+}
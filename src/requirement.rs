@@ -0,0 +1,145 @@
+use crate::prelude::*;
+
+use crate::marker::Marker;
+use crate::version::VersionSpecifier;
+
+// A single parsed PEP 508 dependency specification, e.g. the value of a
+// `Requires-Dist` field:
+//
+//     requests[socks] >=2.8.1 ; python_version < "3.8" and extra == "security"
+//
+// Parsed once here so that dependency resolution can work directly with
+// structured data instead of re-parsing the same strings.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Requirement {
+    pub name: String,
+    pub extras: Vec<String>,
+    pub specifiers: Vec<VersionSpecifier>,
+    // A direct URL reference (`name @ url`), if any. Mutually exclusive with
+    // `specifiers`, which will be empty when this is set.
+    pub url: Option<String>,
+    pub marker: Option<Marker>,
+}
+
+static NAME_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^([A-Za-z0-9](?:[A-Za-z0-9._-]*[A-Za-z0-9])?)").unwrap());
+
+static EXTRA_NAME_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[A-Za-z0-9._-]+$").unwrap());
+
+impl Requirement {
+    pub fn parse(input: &str) -> Result<Requirement> {
+        // The marker, if any, is everything after the first top-level ';' --
+        // none of name/extras/specifier/url syntax can contain one.
+        let (body, marker_str) = match input.find(';') {
+            Some(idx) => (&input[..idx], Some(&input[idx + 1..])),
+            None => (input, None),
+        };
+        let mut rest = body.trim();
+
+        let name_captures = NAME_RE
+            .captures(rest)
+            .ok_or_else(|| anyhow::anyhow!("invalid requirement, no name found: {:?}", input))?;
+        let name = name_captures.get(1).unwrap().as_str().to_string();
+        rest = rest[name_captures.get(0).unwrap().end()..].trim_start();
+
+        let mut extras = Vec::new();
+        if let Some(stripped) = rest.strip_prefix('[') {
+            let close = stripped
+                .find(']')
+                .ok_or_else(|| anyhow::anyhow!("unterminated extras list: {:?}", input))?;
+            for extra in stripped[..close].split(',') {
+                let extra = extra.trim();
+                if !extra.is_empty() {
+                    if !EXTRA_NAME_RE.is_match(extra) {
+                        bail!("invalid extra name {:?} in requirement: {:?}", extra, input);
+                    }
+                    extras.push(extra.to_string());
+                }
+            }
+            rest = stripped[close + 1..].trim_start();
+        }
+
+        let mut url = None;
+        let mut specifiers = Vec::new();
+        if let Some(stripped) = rest.strip_prefix('@') {
+            url = Some(stripped.trim().to_string());
+        } else if !rest.is_empty() {
+            let spec_str = if rest.starts_with('(') && rest.ends_with(')') {
+                &rest[1..rest.len() - 1]
+            } else {
+                rest
+            };
+            if !spec_str.trim().is_empty() {
+                specifiers = VersionSpecifier::parse_set(spec_str)
+                    .with_context(|| format!("invalid version specifier in requirement: {:?}", input))?;
+            }
+        }
+
+        let marker = match marker_str {
+            None => None,
+            Some(marker_str) => Some(
+                Marker::parse(marker_str.trim())
+                    .with_context(|| format!("invalid marker in requirement: {:?}", input))?,
+            ),
+        };
+
+        Ok(Requirement {
+            name,
+            extras,
+            specifiers,
+            url,
+            marker,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::version::VersionOperator;
+
+    #[test]
+    fn test_parse_name_only() {
+        let got = Requirement::parse("requests").unwrap();
+        assert_eq!(got.name, "requests");
+        assert!(got.extras.is_empty());
+        assert!(got.specifiers.is_empty());
+        assert_eq!(got.url, None);
+        assert_eq!(got.marker, None);
+    }
+
+    #[test]
+    fn test_parse_full() {
+        let got =
+            Requirement::parse(r#"requests[socks] >=2.8.1 ; python_version < "3.8" and extra == "security""#)
+                .unwrap();
+        assert_eq!(got.name, "requests");
+        assert_eq!(got.extras, vec!["socks".to_string()]);
+        assert_eq!(got.specifiers.len(), 1);
+        assert_eq!(got.specifiers[0].operator, VersionOperator::GtEq);
+        assert_eq!(got.specifiers[0].version, "2.8.1");
+        assert_eq!(got.url, None);
+        assert!(got.marker.is_some());
+    }
+
+    #[test]
+    fn test_parse_url() {
+        let got = Requirement::parse("requests @ https://example.com/requests.whl").unwrap();
+        assert_eq!(got.name, "requests");
+        assert_eq!(got.url, Some("https://example.com/requests.whl".to_string()));
+        assert!(got.specifiers.is_empty());
+    }
+
+    #[test]
+    fn test_parse_parenthesized_specifiers() {
+        let got = Requirement::parse("requests (>=2.8.1,<3)").unwrap();
+        assert_eq!(got.specifiers.len(), 2);
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(Requirement::parse("").is_err());
+        assert!(Requirement::parse("requests[bad extra]").is_err());
+        assert!(Requirement::parse("requests[socks").is_err());
+    }
+}
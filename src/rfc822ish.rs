@@ -1,14 +1,20 @@
-use anyhow::{Context, Result};
-use std::collections::HashMap;
+use anyhow::{bail, Context, Result};
+use indexmap::IndexMap;
+use std::collections::{HashMap, HashSet};
+
+use crate::requirement::Requirement;
 
 // A parsed version of a package METADATA or PKG-INFO or WHEEL file, as per
 // https://packaging.python.org/specifications/core-metadata/
-pub type Fields = HashMap<String, Vec<String>>;
-
-#[cfg(test)]
-use serde::Deserialize;
-
-#[cfg_attr(test, derive(Debug, Deserialize, PartialEq))]
+//
+// This needs to be insertion-ordered (rather than a plain HashMap) so that
+// serializing a parsed file back out with `RFC822ish`'s `Display` impl keeps
+// fields in the order their names first appeared. All values of a repeated
+// field are written together at that position, so repeats that were
+// interleaved with other fields in the original file come out grouped.
+pub type Fields = IndexMap<String, Vec<String>>;
+
+#[cfg_attr(test, derive(Debug, PartialEq))]
 pub struct RFC822ish {
     pub fields: Fields,
     pub body: Option<String>,
@@ -171,7 +177,173 @@ impl RFC822ish {
     }
 }
 
-pub struct CoreMetadata(Fields);
+// Checks that a field value's embedded newlines, if any, are already shaped
+// as valid continuation lines -- every line after the first starting with a
+// space or tab followed by at least one more character, the way the parser
+// above requires to recognize it as a continuation rather than the start of
+// a new field (a line holding nothing but that one whitespace character
+// doesn't parse).
+//
+// This can only *check* shape, not fix it: the one whitespace character that
+// marks a continuation line is captured as part of the parsed value itself
+// (see `field_value` above), so inventing one for a line that doesn't
+// already start with whitespace would permanently add a byte that was never
+// in the original value, and `parse(x.to_string()) == x` would no longer
+// hold. Values built fresh (not round-tripped through `parse`) must
+// therefore pre-indent any embedded newline themselves.
+fn check_foldable(value: &str) -> Result<()> {
+    for line in value.split('\n').skip(1) {
+        if !line.starts_with(' ') && !line.starts_with('\t') {
+            bail!(
+                "field value has an embedded newline not followed by a space or tab, \
+                 so it can't be written out as a continuation line without losing data: {:?}",
+                value
+            );
+        }
+        if line.len() < 2 || line[1..].starts_with('\r') {
+            bail!(
+                "field value has a continuation line holding only its leading space or tab, \
+                 which can't be parsed back: {:?}",
+                value
+            );
+        }
+    }
+    Ok(())
+}
+
+// Writes fields and body back out in the format `RFC822ish::parse` accepts.
+// Field values are kept as raw UTF-8 -- NOT RFC 2047-encoded -- since the
+// goal is a faithful byte round-trip of the `Fields`/body we parsed,
+// matching what real-world METADATA/WHEEL/PKG-INFO files actually contain.
+impl std::fmt::Display for RFC822ish {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (name, values) in self.fields.iter() {
+            for value in values {
+                check_foldable(value).map_err(|_| std::fmt::Error)?;
+                writeln!(f, "{}: {}", name, value)?;
+            }
+        }
+        if let Some(body) = &self.body {
+            writeln!(f)?;
+            write!(f, "{}", body)?;
+        }
+        Ok(())
+    }
+}
+
+// Metadata field names are nominally case-sensitive, but PyPI has decades of
+// uploads with inconsistent casing (`Name`, `NAME`, `name`...), so field names
+// are normalized here to the canonical spelling when counting them for the
+// single-use check. Lookups (`get_all`/`get_one`) compare names
+// case-insensitively on their own.
+fn canonicalize_field_name(name: &str) -> &'static str {
+    static CANONICAL_NAMES: &[&str] = &[
+        "Metadata-Version",
+        "Name",
+        "Version",
+        "Dynamic",
+        "Platform",
+        "Supported-Platform",
+        "Summary",
+        "Description",
+        "Description-Content-Type",
+        "Keywords",
+        "Home-page",
+        "Download-URL",
+        "Author",
+        "Author-email",
+        "Maintainer",
+        "Maintainer-email",
+        "License",
+        "License-File",
+        "Classifier",
+        "Requires-Dist",
+        "Requires-Python",
+        "Requires-External",
+        "Project-URL",
+        "Provides-Extra",
+        "Provides-Dist",
+        "Obsoletes-Dist",
+    ];
+    CANONICAL_NAMES
+        .iter()
+        .find(|canonical| canonical.eq_ignore_ascii_case(name))
+        .copied()
+        // Not one of the fields core-metadata defines -- keep going rather
+        // than erroring, since extension fields are allowed.
+        .unwrap_or("")
+}
+
+fn get_all<'a>(fields: &'a Fields, field_name: &str) -> Vec<&'a str> {
+    fields
+        .iter()
+        .filter(|(name, _)| name.eq_ignore_ascii_case(field_name))
+        .flat_map(|(_, values)| values.iter())
+        .map(String::as_str)
+        .collect()
+}
+
+fn get_one<'a>(fields: &'a Fields, field_name: &str) -> Option<&'a str> {
+    get_all(fields, field_name).into_iter().next()
+}
+
+// The known values of Metadata-Version we understand, oldest first, along
+// with the fields that became valid to repeat as of that version (fields
+// stay repeatable in every later version too).
+static METADATA_VERSIONS: &[(&str, &[&str])] = &[
+    ("1.0", &["Platform"]),
+    ("1.1", &["Classifier", "Supported-Platform"]),
+    (
+        "1.2",
+        &[
+            "Requires-Dist",
+            "Provides-Dist",
+            "Obsoletes-Dist",
+            "Requires-External",
+            "Project-URL",
+        ],
+    ),
+    ("2.1", &["Provides-Extra"]),
+    ("2.2", &["Dynamic"]),
+    ("2.3", &["License-File"]),
+];
+
+fn multiple_use_fields(metadata_version: &str) -> HashSet<&'static str> {
+    let mut fields = HashSet::new();
+    let mut found = false;
+    for (version, added) in METADATA_VERSIONS.iter() {
+        fields.extend(added.iter());
+        if *version == metadata_version {
+            found = true;
+            break;
+        }
+    }
+    // An unrecognized (presumably newer) Metadata-Version: be lenient and
+    // allow every multi-use field we know about, rather than rejecting files
+    // from a future spec version outright.
+    if !found {
+        for (_, added) in METADATA_VERSIONS.iter() {
+            fields.extend(added.iter());
+        }
+    }
+    fields
+}
+
+// A typed view onto a parsed METADATA/PKG-INFO file, as per
+// https://packaging.python.org/specifications/core-metadata/
+//
+// Field lookups are case-insensitive, since real-world uploads to PyPI have
+// been seen with mixed-case field names (`Name`, `NAME`, etc).
+pub struct CoreMetadata {
+    fields: Fields,
+    pub metadata_version: String,
+    pub name: String,
+    pub version: String,
+    pub requires_python: Option<String>,
+    pub provides_extra: Vec<String>,
+    pub description_content_type: Option<String>,
+    pub requires_dist: Vec<Requirement>,
+}
 
 impl CoreMetadata {
     pub fn parse(data: &str) -> Result<CoreMetadata> {
@@ -183,7 +355,74 @@ impl CoreMetadata {
                 .or_insert(Vec::new())
                 .push(body);
         }
-        Ok(CoreMetadata(rfc822ish.fields))
+        let fields = rfc822ish.fields;
+
+        let metadata_version = get_one(&fields, "Metadata-Version")
+            .context("missing required field Metadata-Version")?
+            .to_string();
+
+        let multi_use = multiple_use_fields(&metadata_version);
+        let mut counts: HashMap<&'static str, usize> = HashMap::new();
+        for (name, values) in fields.iter() {
+            let canonical = canonicalize_field_name(name);
+            if canonical.is_empty() {
+                // Not a field core metadata defines; we don't know its
+                // cardinality rules, so don't second-guess it.
+                continue;
+            }
+            *counts.entry(canonical).or_insert(0) += values.len();
+        }
+        for (canonical, count) in counts {
+            if count > 1 && !multi_use.contains(canonical) {
+                bail!(
+                    "field {} is single-use in Metadata-Version {}, but appeared {} times",
+                    canonical,
+                    metadata_version,
+                    count
+                );
+            }
+        }
+
+        let name = get_one(&fields, "Name")
+            .context("missing required field Name")?
+            .to_string();
+        let version = get_one(&fields, "Version")
+            .context("missing required field Version")?
+            .to_string();
+        let requires_python = get_one(&fields, "Requires-Python").map(str::to_string);
+        let description_content_type =
+            get_one(&fields, "Description-Content-Type").map(str::to_string);
+        let provides_extra = get_all(&fields, "Provides-Extra")
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+
+        let requires_dist = get_all(&fields, "Requires-Dist")
+            .into_iter()
+            .map(Requirement::parse)
+            .collect::<Result<Vec<_>>>()
+            .context("error parsing Requires-Dist")?;
+
+        Ok(CoreMetadata {
+            fields,
+            metadata_version,
+            name,
+            version,
+            requires_python,
+            provides_extra,
+            description_content_type,
+            requires_dist,
+        })
+    }
+
+    // Case-insensitive raw access to any field not promoted to a typed
+    // accessor above (e.g. `Author`, `Home-page`, `Classifier`).
+    pub fn get_all(&self, field_name: &str) -> Vec<&str> {
+        get_all(&self.fields, field_name)
+    }
+
+    pub fn get_one(&self, field_name: &str) -> Option<&str> {
+        get_one(&self.fields, field_name)
     }
 }
 
@@ -192,13 +431,25 @@ mod test {
     use super::*;
     use indoc::indoc;
 
+    // Builds the `Fields` an expectation describes, preserving
+    // insertion order the way the parser does.
+    fn fields_of(pairs: &[(&str, &[&str])]) -> Fields {
+        let mut fields = Fields::new();
+        for (name, values) in pairs {
+            fields.insert(
+                name.to_string(),
+                values.iter().map(|v| v.to_string()).collect(),
+            );
+        }
+        fields
+    }
+
     #[test]
     fn test_successful_parsing() {
         struct T {
-            // Input to parser
             given: &'static str,
-            // Expected parsed data structure, written as json
-            expected: &'static str,
+            expected_fields: &'static [(&'static str, &'static [&'static str])],
+            expected_body: Option<&'static str>,
         }
 
         let test_cases = vec![
@@ -211,20 +462,15 @@ mod test {
                    this is the
                    body!
                 "#},
-                expected: indoc! {r#"
-                   {
-                     "fields": { "A": ["b"], "C": ["d\n   continued"]},
-                     "body": "this is the\nbody!\n"
-                   }
-                "#},
+                expected_fields: &[("A", &["b"]), ("C", &["d\n   continued"])],
+                expected_body: Some("this is the\nbody!\n"),
             },
             T {
                 given: indoc! {r#"
                    no: body
                 "#},
-                expected: indoc! {r#"
-                   {"fields": {"no": ["body"]}}
-                "#},
+                expected_fields: &[("no", &["body"])],
+                expected_body: None,
             },
             T {
                 given: indoc! {r#"
@@ -233,26 +479,98 @@ mod test {
                    another: field
                    duplicate: three
                 "#},
-                expected: indoc! {r#"
-                   {"fields": {"duplicate": ["one", "two", "three"], "another": ["field"]}}
-                "#},
+                expected_fields: &[
+                    ("duplicate", &["one", "two", "three"]),
+                    ("another", &["field"]),
+                ],
+                expected_body: None,
             },
             T {
                 given: indoc! {r#"
                    no: trailing newline"#},
-                expected: indoc! {r#"
-                   {"fields": {"no": ["trailing newline"]}}
-                "#},
+                expected_fields: &[("no", &["trailing newline"])],
+                expected_body: None,
             },
         ];
 
         for test_case in test_cases {
             let got = RFC822ish::parse(test_case.given).unwrap();
-            let expected: RFC822ish = serde_json::from_str(test_case.expected).unwrap();
+            let expected = RFC822ish {
+                fields: fields_of(test_case.expected_fields),
+                body: test_case.expected_body.map(String::from),
+            };
             assert_eq!(got, expected);
         }
     }
 
+    #[test]
+    fn test_roundtrip() {
+        let given_cases = vec![
+            indoc! {r#"
+               A: b
+               C: d
+                  continued
+
+               this is the
+               body!
+            "#},
+            indoc! {r#"
+               no: body
+            "#},
+            indoc! {r#"
+               duplicate: one
+               duplicate: two
+               another: field
+               duplicate: three
+            "#},
+        ];
+
+        for given in given_cases {
+            let parsed = RFC822ish::parse(given).unwrap();
+            let serialized = parsed.to_string();
+            let reparsed = RFC822ish::parse(&serialized).unwrap();
+            assert_eq!(parsed, reparsed);
+        }
+    }
+
+    // A freshly-built (not parsed) multi-paragraph value whose continuation
+    // lines are already pre-indented round-trips fine, since nothing needs
+    // to be invented to make it parseable again.
+    #[test]
+    fn test_roundtrip_preindented_freshly_built_value() {
+        // Every line after the first needs its own leading whitespace to be
+        // a valid continuation -- including a deliberately-blank-looking
+        // paragraph break, which has to be a line of pure whitespace rather
+        // than a truly empty one (a truly empty line ends the header block
+        // instead of continuing it). That line needs at least two whitespace
+        // characters: the parser requires something after the one that marks
+        // the continuation.
+        let given = RFC822ish {
+            fields: fields_of(&[("Description", &["paragraph one\n  \n paragraph two"])]),
+            body: None,
+        };
+        let serialized = given.to_string();
+        let reparsed = RFC822ish::parse(&serialized).unwrap();
+        assert_eq!(given, reparsed);
+    }
+
+    // A freshly-built multi-paragraph value with a *bare*, unindented blank
+    // line can't be folded into a continuation line without inventing a
+    // whitespace byte that was never in the original value -- and that
+    // invented byte would become part of the value on the next parse,
+    // silently corrupting it. Serializing must fail instead of doing that.
+    #[test]
+    fn test_display_rejects_unindented_embedded_newline() {
+        use std::fmt::Write;
+
+        let given = RFC822ish {
+            fields: fields_of(&[("Description", &["paragraph one\n\nparagraph two"])]),
+            body: None,
+        };
+        let mut buf = String::new();
+        assert!(write!(buf, "{}", given).is_err());
+    }
+
     #[test]
     fn test_failed_parsing() {
         let test_cases = vec![
@@ -274,4 +592,42 @@ mod test {
             assert!(got.is_err());
         }
     }
+
+    #[test]
+    fn test_core_metadata_rejects_repeated_single_use_field() {
+        let given = indoc! {r#"
+           Metadata-Version: 1.0
+           Name: foo
+           Version: 1.0
+           Version: 2.0
+        "#};
+        assert!(CoreMetadata::parse(given).is_err());
+    }
+
+    // A single-use field repeated under a different case (`Version` then
+    // `VERSION`) is the exact mixed-casing scenario case-insensitive lookup
+    // exists for; it must be rejected the same as same-case repetition.
+    #[test]
+    fn test_core_metadata_rejects_repeated_single_use_field_mixed_case() {
+        let given = indoc! {r#"
+           Metadata-Version: 1.0
+           Name: foo
+           Version: 1.0
+           VERSION: 2.0
+        "#};
+        assert!(CoreMetadata::parse(given).is_err());
+    }
+
+    #[test]
+    fn test_core_metadata_allows_repeated_multi_use_field() {
+        let given = indoc! {r#"
+           Metadata-Version: 1.1
+           Name: foo
+           Version: 1.0
+           Classifier: A
+           Classifier: B
+        "#};
+        let metadata = CoreMetadata::parse(given).unwrap();
+        assert_eq!(metadata.get_all("Classifier"), vec!["A", "B"]);
+    }
 }
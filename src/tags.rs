@@ -0,0 +1,186 @@
+use crate::prelude::*;
+
+use crate::platform_tags;
+
+// Computes the full, ordered list of wheel compatibility tags
+// ("{interpreter}-{abi}-{platform}") that a given Python accepts, matching
+// the ordering used by the `packaging` library's `sys_tags()`: most
+// preferred (most specific) first, so the first wheel found to match one of
+// these tags is the best available match.
+//
+// `implementation` is the short interpreter tag, e.g. "cp" or "pp".
+// `version` is `(major, minor)`, e.g. `(3, 10)`.
+// `abis` are the ABI tags the interpreter itself reports as compatible (as
+// returned by e.g. `sysconfig`), most-specific first; "abi3" among them
+// triggers the stable-ABI ladder below.
+pub fn sys_tags(implementation: &str, version: (u32, u32), abis: &[String]) -> Result<Vec<String>> {
+    let plats = platform_tags::platform_tags()?;
+    Ok(sys_tags_for_platforms(implementation, version, abis, &plats))
+}
+
+// The platform-independent half of `sys_tags`: combines interpreter/ABI with
+// an already-computed list of platform tags. Split out from `sys_tags` so
+// this combinatorial logic can be tested without depending on the host's
+// actual OS/arch detection.
+fn sys_tags_for_platforms(
+    implementation: &str,
+    version: (u32, u32),
+    abis: &[String],
+    plats: &[String],
+) -> Vec<String> {
+    let (major, minor) = version;
+    let mut tags: Vec<String> = Vec::new();
+
+    let interp = format!("{}{}{}", implementation, major, minor);
+
+    if implementation == "cp" {
+        // CPython-specific tags, e.g. cp310-cp310-<plat>
+        for abi in abis
+            .iter()
+            .filter(|abi| abi.as_str() != "abi3" && abi.as_str() != "none")
+        {
+            for plat in plats {
+                tags.push(format!("{}-{}-{}", interp, abi, plat));
+            }
+        }
+
+        // Stable ABI ladder: cp310-abi3-<plat> down through cp32-abi3-<plat>,
+        // since a stable-ABI wheel built against any older minor version is
+        // still loadable by this interpreter.
+        if abis.iter().any(|abi| abi.as_str() == "abi3") {
+            for m in (2..=minor).rev() {
+                let abi3_interp = format!("cp{}{}", major, m);
+                for plat in plats {
+                    tags.push(format!("{}-abi3-{}", abi3_interp, plat));
+                }
+            }
+        }
+
+        // Fully generic CPython tag.
+        for plat in plats {
+            tags.push(format!("{}-none-{}", interp, plat));
+        }
+    } else {
+        for abi in abis.iter().filter(|abi| abi.as_str() != "none") {
+            for plat in plats {
+                tags.push(format!("{}-{}-{}", interp, abi, plat));
+            }
+        }
+        for plat in plats {
+            tags.push(format!("{}-none-{}", interp, plat));
+        }
+    }
+
+    // py3X/py3 "none"-ABI fallbacks: pure-Python wheels that only declare a
+    // minimum minor version, most-specific minor first.
+    for m in (0..=minor).rev() {
+        let py_interp = format!("py{}{}", major, m);
+        for plat in plats {
+            tags.push(format!("{}-none-{}", py_interp, plat));
+        }
+    }
+    for plat in plats {
+        tags.push(format!("py{}-none-{}", major, plat));
+    }
+
+    // Finally, the universal "-none-any" tags, for wheels that don't care
+    // about platform at all.
+    for m in (0..=minor).rev() {
+        tags.push(format!("py{}{}-none-any", major, m));
+    }
+    tags.push(format!("{}-none-any", interp));
+    tags.push(format!("py{}-none-any", major));
+
+    tags
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn strs(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_cpython_without_abi3() {
+        let plats = strs(&["linux_x86_64"]);
+        let abis = strs(&["cp310"]);
+        let got = sys_tags_for_platforms("cp", (3, 10), &abis, &plats);
+        assert_eq!(
+            got,
+            vec![
+                "cp310-cp310-linux_x86_64",
+                "cp310-none-linux_x86_64",
+                "py310-none-linux_x86_64",
+                "py39-none-linux_x86_64",
+                "py38-none-linux_x86_64",
+                "py37-none-linux_x86_64",
+                "py36-none-linux_x86_64",
+                "py35-none-linux_x86_64",
+                "py34-none-linux_x86_64",
+                "py33-none-linux_x86_64",
+                "py32-none-linux_x86_64",
+                "py31-none-linux_x86_64",
+                "py30-none-linux_x86_64",
+                "py3-none-linux_x86_64",
+                "py310-none-any",
+                "py39-none-any",
+                "py38-none-any",
+                "py37-none-any",
+                "py36-none-any",
+                "py35-none-any",
+                "py34-none-any",
+                "py33-none-any",
+                "py32-none-any",
+                "py31-none-any",
+                "py30-none-any",
+                "cp310-none-any",
+                "py3-none-any",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cpython_with_abi3() {
+        let plats = strs(&["linux_x86_64"]);
+        let abis = strs(&["cp310", "abi3"]);
+        let got = sys_tags_for_platforms("cp", (3, 10), &abis, &plats);
+        // The CPython-specific tag comes first, then the full abi3 ladder
+        // down to cp32, ahead of the fully generic cp310-none tag.
+        assert_eq!(got[0], "cp310-cp310-linux_x86_64");
+        assert_eq!(
+            got[1..9],
+            [
+                "cp310-abi3-linux_x86_64",
+                "cp39-abi3-linux_x86_64",
+                "cp38-abi3-linux_x86_64",
+                "cp37-abi3-linux_x86_64",
+                "cp36-abi3-linux_x86_64",
+                "cp35-abi3-linux_x86_64",
+                "cp34-abi3-linux_x86_64",
+                "cp33-abi3-linux_x86_64",
+            ]
+        );
+        assert_eq!(got[9], "cp32-abi3-linux_x86_64");
+        assert_eq!(got[10], "cp310-none-linux_x86_64");
+    }
+
+    #[test]
+    fn test_non_cpython_implementation() {
+        let plats = strs(&["linux_x86_64"]);
+        let abis = strs(&["pp310"]);
+        let got = sys_tags_for_platforms("pp", (3, 10), &abis, &plats);
+        assert_eq!(got[0], "pp310-pp310-linux_x86_64");
+        assert_eq!(got[1], "pp310-none-linux_x86_64");
+    }
+
+    #[test]
+    fn test_multiple_platforms_preserve_order() {
+        let plats = strs(&["linux_x86_64", "linux_i686"]);
+        let abis = strs(&["cp310"]);
+        let got = sys_tags_for_platforms("cp", (3, 10), &abis, &plats);
+        assert_eq!(got[0], "cp310-cp310-linux_x86_64");
+        assert_eq!(got[1], "cp310-cp310-linux_i686");
+    }
+}
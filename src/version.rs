@@ -0,0 +1,109 @@
+use crate::prelude::*;
+
+// A single clause of a PEP 440 version specifier set, e.g. the `>=2.8.1` in
+// `requests >=2.8.1, <3`. We keep the version itself as the raw string here
+// rather than parsing it into a structured `Version` -- that's a large
+// grammar in its own right, and nothing downstream of `Requires-Dist`
+// parsing needs it yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionSpecifier {
+    pub operator: VersionOperator,
+    pub version: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionOperator {
+    Compatible,  // ~=
+    Eq,          // ==
+    NotEq,       // !=
+    LtEq,        // <=
+    GtEq,        // >=
+    Lt,          // <
+    Gt,          // >
+    ArbitraryEq, // ===
+}
+
+static SPECIFIER_RE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(~=|===|==|!=|<=|>=|<|>)\s*(.+?)\s*$").unwrap());
+
+impl VersionSpecifier {
+    pub fn parse(input: &str) -> Result<VersionSpecifier> {
+        let input = input.trim();
+        let captures = SPECIFIER_RE
+            .captures(input)
+            .ok_or_else(|| anyhow::anyhow!("invalid version specifier: {:?}", input))?;
+        let operator = match captures.get(1).unwrap().as_str() {
+            "~=" => VersionOperator::Compatible,
+            "==" => VersionOperator::Eq,
+            "!=" => VersionOperator::NotEq,
+            "<=" => VersionOperator::LtEq,
+            ">=" => VersionOperator::GtEq,
+            "<" => VersionOperator::Lt,
+            ">" => VersionOperator::Gt,
+            "===" => VersionOperator::ArbitraryEq,
+            other => bail!("unreachable version operator: {}", other),
+        };
+        let version = captures.get(2).unwrap().as_str().to_string();
+        Ok(VersionSpecifier { operator, version })
+    }
+
+    // Parses a comma-separated specifier set, e.g. `>=2.8.1,!=2.9.*,<3`.
+    pub fn parse_set(input: &str) -> Result<Vec<VersionSpecifier>> {
+        input.split(',').map(VersionSpecifier::parse).collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_operators() {
+        let test_cases = vec![
+            ("~=2.8.1", VersionOperator::Compatible, "2.8.1"),
+            ("==2.8.1", VersionOperator::Eq, "2.8.1"),
+            ("!=2.8.1", VersionOperator::NotEq, "2.8.1"),
+            ("<=2.8.1", VersionOperator::LtEq, "2.8.1"),
+            (">=2.8.1", VersionOperator::GtEq, "2.8.1"),
+            ("<2.8.1", VersionOperator::Lt, "2.8.1"),
+            (">2.8.1", VersionOperator::Gt, "2.8.1"),
+            // Arbitrary equality must win out over plain `==`, even though
+            // `==` is a prefix of `===`.
+            ("===2.8.1", VersionOperator::ArbitraryEq, "2.8.1"),
+            ("  >= 2.8.1  ", VersionOperator::GtEq, "2.8.1"),
+        ];
+        for (given, expected_operator, expected_version) in test_cases {
+            let got = VersionSpecifier::parse(given).unwrap();
+            assert_eq!(got.operator, expected_operator);
+            assert_eq!(got.version, expected_version);
+        }
+    }
+
+    #[test]
+    fn test_parse_invalid() {
+        assert!(VersionSpecifier::parse("2.8.1").is_err());
+        assert!(VersionSpecifier::parse("").is_err());
+    }
+
+    #[test]
+    fn test_parse_set() {
+        let got = VersionSpecifier::parse_set(">=2.8.1,!=2.9.*,<3").unwrap();
+        assert_eq!(
+            got,
+            vec![
+                VersionSpecifier {
+                    operator: VersionOperator::GtEq,
+                    version: "2.8.1".to_string(),
+                },
+                VersionSpecifier {
+                    operator: VersionOperator::NotEq,
+                    version: "2.9.*".to_string(),
+                },
+                VersionSpecifier {
+                    operator: VersionOperator::Lt,
+                    version: "3".to_string(),
+                },
+            ]
+        );
+    }
+}